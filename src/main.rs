@@ -1,20 +1,201 @@
 #![no_main]
 #![no_std]
 
+extern crate alloc;
+
+use alloc::rc::Rc;
+use core::cell::{Cell, RefCell};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
 use core::time::Duration;
 
-use bytemuck::{Pod, Zeroable, bytes_of, from_bytes};
+use bytemuck::{Pod, Zeroable, bytes_of, from_bytes, pod_read_unaligned};
 use vexide::{io::Result, prelude::*, time};
 
 const BAUD_RATE: u32 = 115200;
-const MOTOR_PACKET_MAGIC: u16 = 0xFEFA;
+const COMMAND_MAGIC: u16 = 0xFEFA;
 const ENCODER_PACKET_MAGIC: u16 = 0xF23B;
+const RESPONSE_MAGIC: u16 = 0xC0A5;
+const TELEMETRY_PACKET_MAGIC: u16 = 0xA17E;
+const TIME_SYNC_MAGIC: u16 = 0xC1DE;
 const MOTOR_POWER_MAX: f64 = 1.0;
 const MOTOR_VOLTAGE_MAX: f64 = 12.0;
+/// Reported by `PING` and the `FirmwareVersion` register; bump on protocol changes.
+const FIRMWARE_VERSION: u32 = 1;
+
+/// If no valid power packet arrives within this long, the link is assumed
+/// dead (cable unplug, host crash) and every motor is commanded to zero
+/// voltage rather than holding its last commanded output forever.
+const WATCHDOG_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Longest interval between two runs of the motor task, which reapplies the
+/// latest power packet, checks the watchdog, and emits a telemetry packet.
+/// A fresh power packet also wakes the task early and restarts this interval,
+/// so a quiet link still gets an update at least this often.
+const MOTOR_UPDATE_CADENCE: Duration = Duration::from_millis(20);
+
+/// Largest payload `read_frame` accepts. Inbound command payloads are well
+/// under this, so a fixed stack buffer avoids needing `alloc`; outbound frames
+/// (such as the larger `TelemetryPacket`) are not bound by it.
+const MAX_PAYLOAD_LEN: usize = 64;
+
+const MOTORS_PER_GROUP: usize = 2;
+const MOTOR_GROUP_COUNT: usize = 4;
+const MOTOR_COUNT: usize = MOTOR_GROUP_COUNT * MOTORS_PER_GROUP;
+
+/// CRC-16/IBM (aka CRC-16/ARC): poly 0x8005, init 0x0000, reflected in/out.
+/// Computed over the payload bytes only, not the magic or length header.
+fn crc16_ibm(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0x0000;
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            if crc & 1 != 0 {
+                crc = (crc >> 1) ^ 0xA001;
+            } else {
+                crc >>= 1;
+            }
+        }
+    }
+    crc
+}
+
+/// Reads one `[magic u16][payload_len u16][payload][crc16]` frame matching
+/// `magic`, scanning for resync on a dropped or corrupted byte. Returns the
+/// payload bytes (and their length) once the trailing CRC checks out.
+async fn read_frame(
+    rx_port: &mut SerialPort,
+    magic: u16,
+    timeout: Duration,
+) -> Option<([u8; MAX_PAYLOAD_LEN], usize)> {
+    let start_time = time::Instant::now();
+    while time::Instant::now().duration_since(start_time) < timeout {
+        // Check for the whole magic, byte by byte
+        if magic
+            .to_le_bytes()
+            .iter()
+            .all(|x| rx_port.read_byte() == Some(*x))
+        {
+            let mut len_bytes = [0u8; 2];
+            if rx_port.read_exact(&mut len_bytes).is_err() {
+                continue;
+            }
+            let payload_len = u16::from_le_bytes(len_bytes) as usize;
+            if payload_len > MAX_PAYLOAD_LEN {
+                continue;
+            }
+
+            let mut payload = [0u8; MAX_PAYLOAD_LEN];
+            if rx_port.read_exact(&mut payload[..payload_len]).is_err() {
+                continue;
+            }
+
+            let mut crc_bytes = [0u8; 2];
+            if rx_port.read_exact(&mut crc_bytes).is_err() {
+                continue;
+            }
+
+            if crc16_ibm(&payload[..payload_len]) == u16::from_le_bytes(crc_bytes) {
+                return Some((payload, payload_len));
+            }
+            // CRC mismatch: drop the frame and resume scanning for magic.
+        }
+
+        // Yield between scan attempts so a quiet line doesn't starve the
+        // motor-update task; see `rx_task` and `motor_task` below for how the
+        // two tasks share the executor.
+        vexide::task::yield_now().await;
+    }
+
+    None
+}
+
+/// Writes one `[magic u16][payload_len u16][payload][crc16]` frame.
+fn write_frame(tx_port: &mut SerialPort, magic: u16, payload: &[u8]) -> Result<()> {
+    tx_port.write_all(&magic.to_le_bytes())?;
+    tx_port.write_all(&(payload.len() as u16).to_le_bytes())?;
+    tx_port.write_all(payload)?;
+    tx_port.write_all(&crc16_ibm(payload).to_le_bytes())?;
+
+    Ok(())
+}
+
+/// Stack-pins a local future in place, the `alloc`-free equivalent of
+/// `futures::pin_mut!`. Used by [`select`] so that it pins both futures on
+/// the stack rather than making a `Box::pin` heap allocation per call.
+macro_rules! pin_mut {
+    ($($x:ident),* $(,)?) => {
+        $(
+            let mut $x = $x;
+            #[allow(unused_mut)]
+            let mut $x = unsafe { Pin::new_unchecked(&mut $x) };
+        )*
+    };
+}
+
+/// Races two futures, resolving as soon as either one completes. There's no
+/// executor-provided `select!` here, so this is a minimal hand-rolled stand-in
+/// in the spirit of the one embassy and blflash compose their async transports
+/// with.
+async fn select<A: Future<Output = ()>, B: Future<Output = ()>>(a: A, b: B) {
+    pin_mut!(a, b);
+    core::future::poll_fn(|cx: &mut Context<'_>| {
+        if a.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(());
+        }
+        if b.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+/// A single-slot async wakeup: [`Signal::notify`] latches "fired" and wakes
+/// whoever is parked in [`Signal::wait`], instead of that waiter having to
+/// busy-poll a flag between timer ticks.
+#[derive(Default)]
+struct Signal {
+    fired: Cell<bool>,
+    waker: RefCell<Option<Waker>>,
+}
+
+impl Signal {
+    fn notify(&self) {
+        self.fired.set(true);
+        if let Some(waker) = self.waker.borrow_mut().take() {
+            waker.wake();
+        }
+    }
+
+    fn wait(&self) -> SignalWait<'_> {
+        SignalWait { signal: self }
+    }
+}
+
+struct SignalWait<'a> {
+    signal: &'a Signal,
+}
+
+impl Future for SignalWait<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.signal.fired.take() {
+            Poll::Ready(())
+        } else {
+            *self.signal.waker.borrow_mut() = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
 
 #[derive(Clone, Copy, Pod, Debug)]
 #[repr(C)]
 struct MotorPacket {
+    /// Monotonic capture/send time in microseconds; see [`Drivetrain::timestamp_micros`].
+    timestamp_micros: u64,
     front_left: f64,
     front_right: f64,
     back_left: f64,
@@ -24,6 +205,7 @@ struct MotorPacket {
 unsafe impl Zeroable for MotorPacket {
     fn zeroed() -> Self {
         MotorPacket {
+            timestamp_micros: 0,
             front_left: 0.,
             front_right: 0.,
             back_left: 0.,
@@ -32,96 +214,746 @@ unsafe impl Zeroable for MotorPacket {
     }
 }
 
-fn get_power_packet(rx_port: &mut SerialPort) -> Option<MotorPacket> {
+/// Inbound command byte, sent as the first payload byte of a `COMMAND_MAGIC` frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum Command {
+    Ping = 0,
+    ReadReg = 1,
+    WriteReg = 2,
+    SetPower = 3,
+    TimeSync = 4,
+}
+
+impl Command {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Ping),
+            1 => Some(Self::ReadReg),
+            2 => Some(Self::WriteReg),
+            3 => Some(Self::SetPower),
+            4 => Some(Self::TimeSync),
+            _ => None,
+        }
+    }
+}
+
+/// Addressable per-motor configuration registers. `FirmwareVersion` and
+/// `TelemetryMode` are global; reads and writes to them ignore `motor_index`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum Register {
+    Gearset = 0,
+    Direction = 1,
+    BrakeMode = 2,
+    VoltageLimit = 3,
+    FirmwareVersion = 4,
+    TelemetryMode = 5,
+}
+
+impl Register {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Gearset),
+            1 => Some(Self::Direction),
+            2 => Some(Self::BrakeMode),
+            3 => Some(Self::VoltageLimit),
+            4 => Some(Self::FirmwareVersion),
+            5 => Some(Self::TelemetryMode),
+            _ => None,
+        }
+    }
+}
+
+/// Selects which packet the main loop emits each cycle. `Encoder` is the
+/// original lightweight, position-only packet for low-bandwidth consumers;
+/// `Full` is the richer [`TelemetryPacket`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum TelemetryMode {
+    Encoder = 0,
+    Full = 1,
+}
+
+impl TelemetryMode {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Encoder),
+            1 => Some(Self::Full),
+            _ => None,
+        }
+    }
+}
+
+/// Fixed-width little-endian register value. Only the bytes a given
+/// register actually uses are meaningful; the rest are zero.
+type RegisterValue = [u8; 8];
+
+fn encode_u8_register(value: u8) -> RegisterValue {
+    let mut bytes = RegisterValue::default();
+    bytes[0] = value;
+    bytes
+}
+
+fn encode_u32_register(value: u32) -> RegisterValue {
+    let mut bytes = RegisterValue::default();
+    bytes[..4].copy_from_slice(&value.to_le_bytes());
+    bytes
+}
+
+fn encode_f64_register(value: f64) -> RegisterValue {
+    value.to_le_bytes()
+}
+
+fn decode_f64_register(value: RegisterValue) -> f64 {
+    f64::from_le_bytes(value)
+}
+
+fn gearset_to_byte(gearset: Gearset) -> u8 {
+    match gearset {
+        Gearset::Red => 0,
+        Gearset::Green => 1,
+        Gearset::Blue => 2,
+    }
+}
+
+fn gearset_from_byte(byte: u8) -> Option<Gearset> {
+    match byte {
+        0 => Some(Gearset::Red),
+        1 => Some(Gearset::Green),
+        2 => Some(Gearset::Blue),
+        _ => None,
+    }
+}
+
+fn direction_to_byte(direction: Direction) -> u8 {
+    match direction {
+        Direction::Forward => 0,
+        Direction::Reverse => 1,
+    }
+}
+
+fn direction_from_byte(byte: u8) -> Option<Direction> {
+    match byte {
+        0 => Some(Direction::Forward),
+        1 => Some(Direction::Reverse),
+        _ => None,
+    }
+}
+
+fn brake_mode_to_byte(brake_mode: BrakeMode) -> u8 {
+    match brake_mode {
+        BrakeMode::Coast => 0,
+        BrakeMode::Brake => 1,
+        BrakeMode::Hold => 2,
+    }
+}
+
+fn brake_mode_from_byte(byte: u8) -> Option<BrakeMode> {
+    match byte {
+        0 => Some(BrakeMode::Coast),
+        1 => Some(BrakeMode::Brake),
+        2 => Some(BrakeMode::Hold),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct ReadRegRequest {
+    motor_index: u8,
+    register: u8,
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct WriteRegRequest {
+    motor_index: u8,
+    register: u8,
+    value: RegisterValue,
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct CommandResponse {
+    command: u8,
+    motor_index: u8,
+    register: u8,
+    _reserved: u8,
+    value: RegisterValue,
+}
+
+/// Full feedback state for a single motor, reported every loop under
+/// [`TelemetryMode::Full`].
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct MotorTelemetry {
+    position_degrees: f64,
+    velocity_rpm: f64,
+    current_amps: f64,
+    torque_nm: f64,
+    temperature_celsius: f64,
+    voltage: f64,
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct TelemetryPacket {
+    /// Monotonic capture time in microseconds; see [`Drivetrain::timestamp_micros`].
+    timestamp_micros: u64,
+    motors: [MotorTelemetry; MOTOR_COUNT],
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct TimeSyncRequest {
+    host_timestamp_micros: u64,
+}
+
+/// Echoes the host's clock back alongside the brain's own capture time so
+/// the host can compute offset and round-trip latency.
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct TimeSyncResponse {
+    host_timestamp_micros: u64,
+    brain_timestamp_micros: u64,
+}
+
+/// A fully parsed inbound command, decoded from a `COMMAND_MAGIC` frame.
+enum BridgeCommand {
+    Ping,
+    ReadReg {
+        motor_index: u8,
+        register: Register,
+    },
+    WriteReg {
+        motor_index: u8,
+        register: Register,
+        value: RegisterValue,
+    },
+    SetPower(MotorPacket),
+    TimeSync {
+        host_timestamp_micros: u64,
+    },
+}
+
+async fn get_command(rx_port: &mut SerialPort) -> Option<BridgeCommand> {
     const TIMEOUT: Duration = Duration::from_secs(1);
 
-    let start_time = time::Instant::now();
-    while time::Instant::now().duration_since(start_time) < TIMEOUT {
-        // Check for the whole magic, byte by byte
-        if MOTOR_PACKET_MAGIC
-            .to_le_bytes()
-            .iter()
-            .all(|x| rx_port.read_byte() == Some(*x))
-        {
-            let mut packet = [0u8; core::mem::size_of::<MotorPacket>()];
-            if rx_port.read_exact(&mut packet).is_ok() {
-                return Some(*from_bytes(&packet));
-            }
+    let (payload, payload_len) = read_frame(rx_port, COMMAND_MAGIC, TIMEOUT).await?;
+    let (&command_byte, rest) = payload[..payload_len].split_first()?;
+
+    match Command::from_byte(command_byte)? {
+        Command::Ping => Some(BridgeCommand::Ping),
+        Command::ReadReg => {
+            let req: &ReadRegRequest =
+                from_bytes(rest.get(..core::mem::size_of::<ReadRegRequest>())?);
+            Some(BridgeCommand::ReadReg {
+                motor_index: req.motor_index,
+                register: Register::from_byte(req.register)?,
+            })
+        }
+        Command::WriteReg => {
+            let req: &WriteRegRequest =
+                from_bytes(rest.get(..core::mem::size_of::<WriteRegRequest>())?);
+            Some(BridgeCommand::WriteReg {
+                motor_index: req.motor_index,
+                register: Register::from_byte(req.register)?,
+                value: req.value,
+            })
+        }
+        Command::SetPower => {
+            // `rest` starts at offset 1 into the payload (past the command
+            // byte), so it isn't guaranteed aligned for `MotorPacket`'s
+            // 8-byte fields; `pod_read_unaligned` copies instead of casting.
+            let packet: MotorPacket =
+                pod_read_unaligned(rest.get(..core::mem::size_of::<MotorPacket>())?);
+            Some(BridgeCommand::SetPower(packet))
+        }
+        Command::TimeSync => {
+            // Same unaligned-`rest` hazard as `SetPower`: `TimeSyncRequest`
+            // carries a `u64`, so this must copy rather than cast.
+            let req: TimeSyncRequest =
+                pod_read_unaligned(rest.get(..core::mem::size_of::<TimeSyncRequest>())?);
+            Some(BridgeCommand::TimeSync {
+                host_timestamp_micros: req.host_timestamp_micros,
+            })
         }
     }
+}
 
-    None
+fn send_response(tx_port: &mut SerialPort, response: &CommandResponse) -> Result<()> {
+    write_frame(tx_port, RESPONSE_MAGIC, bytes_of(response))
 }
 
 fn send_encoder_packet(tx_port: &mut SerialPort, packet: &MotorPacket) -> Result<()> {
-    tx_port.write_all(&ENCODER_PACKET_MAGIC.to_le_bytes())?;
-    tx_port.write_all(bytes_of(packet))?;
+    write_frame(tx_port, ENCODER_PACKET_MAGIC, bytes_of(packet))
+}
 
-    Ok(())
+fn send_telemetry_packet(tx_port: &mut SerialPort, packet: &TelemetryPacket) -> Result<()> {
+    write_frame(tx_port, TELEMETRY_PACKET_MAGIC, bytes_of(packet))
 }
 
-#[vexide::main]
-async fn main(peripherals: Peripherals) {
-    let mut rx_serial = SerialPort::open(peripherals.port_1, BAUD_RATE).await;
-    let mut tx_serial = SerialPort::open(peripherals.port_2, BAUD_RATE).await;
-    let mut front_lefts: [Motor; _] = [
-        Motor::new(peripherals.port_3, Gearset::Green, Direction::Forward),
-        Motor::new(peripherals.port_4, Gearset::Green, Direction::Forward),
-    ];
-    let mut front_rights: [Motor; 2] = [
-        Motor::new(peripherals.port_5, Gearset::Green, Direction::Forward),
-        Motor::new(peripherals.port_6, Gearset::Green, Direction::Forward),
-    ];
-    let mut back_lefts: [Motor; 2] = [
-        Motor::new(peripherals.port_7, Gearset::Green, Direction::Forward),
-        Motor::new(peripherals.port_8, Gearset::Green, Direction::Forward),
-    ];
-    let mut back_rights: [Motor; 2] = [
-        Motor::new(peripherals.port_9, Gearset::Green, Direction::Forward),
-        Motor::new(peripherals.port_10, Gearset::Green, Direction::Forward),
-    ];
+fn send_time_sync_response(tx_port: &mut SerialPort, response: &TimeSyncResponse) -> Result<()> {
+    write_frame(tx_port, TIME_SYNC_MAGIC, bytes_of(response))
+}
+
+/// Commands every motor in every group to zero voltage, logging (rather than
+/// panicking on) any individual motor that fails to respond.
+fn failsafe_motors(motor_groups: &mut [&mut [Motor]]) {
+    for group in motor_groups.iter_mut() {
+        for motor in group.iter_mut() {
+            if let Err(e) = motor.set_voltage(0.0) {
+                println!("Failsafe: motor stop failed: {:?}", e);
+            }
+        }
+    }
+}
 
+/// Reads a motor's position in degrees, logging (rather than panicking on)
+/// a failed read and reporting 0.0 in its place.
+fn read_position_degrees(motor: &mut Motor, label: &str) -> f64 {
+    match motor.position() {
+        Ok(position) => position.as_degrees(),
+        Err(e) => {
+            println!("{label} position read failed: {e:?}");
+            0.0
+        }
+    }
+}
+
+/// Unwraps a motor feedback reading, logging (rather than panicking on) a
+/// failed read and reporting 0.0 in its place.
+fn read_feedback_value<E: core::fmt::Debug>(
+    reading: core::result::Result<f64, E>,
+    label: &str,
+    field: &str,
+) -> f64 {
+    match reading {
+        Ok(value) => value,
+        Err(e) => {
+            println!("{label} {field} read failed: {e:?}");
+            0.0
+        }
+    }
+}
+
+/// Reads the full feedback state of a single motor. Every getter here reads
+/// live state off the smart port motor, which can fail (disconnected or
+/// misbehaving motor) exactly like `position` already does, so all five are
+/// handled the same fallible way via `read_feedback_value`.
+fn motor_telemetry(motor: &mut Motor, label: &str) -> MotorTelemetry {
+    MotorTelemetry {
+        position_degrees: read_position_degrees(motor, label),
+        velocity_rpm: read_feedback_value(motor.velocity(), label, "velocity"),
+        current_amps: read_feedback_value(motor.current(), label, "current"),
+        torque_nm: read_feedback_value(motor.torque(), label, "torque"),
+        temperature_celsius: read_feedback_value(motor.temperature(), label, "temperature"),
+        voltage: read_feedback_value(motor.voltage(), label, "voltage"),
+    }
+}
+
+/// Sets each motor in a group to `power` scaled by its own voltage limit,
+/// logging (rather than panicking on) any individual motor that fails.
+fn set_group_power(motors: &mut [Motor], voltage_limits: &[f64], power: f64, label: &str) {
+    for (motor, &voltage_limit) in motors.iter_mut().zip(voltage_limits) {
+        if let Err(e) = motor.set_voltage(power * voltage_limit / MOTOR_POWER_MAX) {
+            println!("{label} motor set failed: {e:?}");
+        }
+    }
+}
+
+/// The four drivetrain motor groups, plus bridge-side per-motor config (the
+/// voltage limit used to scale commanded power into volts) that isn't
+/// otherwise tracked by the motor itself.
+struct Drivetrain {
+    front_left: [Motor; MOTORS_PER_GROUP],
+    front_right: [Motor; MOTORS_PER_GROUP],
+    back_left: [Motor; MOTORS_PER_GROUP],
+    back_right: [Motor; MOTORS_PER_GROUP],
+    voltage_limits: [f64; MOTOR_COUNT],
+    telemetry_mode: TelemetryMode,
+    /// Reference instant all outgoing timestamps are measured from.
+    boot_instant: time::Instant,
+}
+
+impl Drivetrain {
+    /// Microseconds elapsed since `boot_instant`, used to timestamp every
+    /// outgoing packet.
+    fn timestamp_micros(&self) -> u64 {
+        time::Instant::now()
+            .duration_since(self.boot_instant)
+            .as_micros() as u64
+    }
+
+    fn motor_mut(&mut self, motor_index: u8) -> Option<&mut Motor> {
+        match motor_index {
+            0 => Some(&mut self.front_left[0]),
+            1 => Some(&mut self.front_left[1]),
+            2 => Some(&mut self.front_right[0]),
+            3 => Some(&mut self.front_right[1]),
+            4 => Some(&mut self.back_left[0]),
+            5 => Some(&mut self.back_left[1]),
+            6 => Some(&mut self.back_right[0]),
+            7 => Some(&mut self.back_right[1]),
+            _ => None,
+        }
+    }
+
+    fn failsafe(&mut self) {
+        failsafe_motors(&mut [
+            &mut self.front_left,
+            &mut self.front_right,
+            &mut self.back_left,
+            &mut self.back_right,
+        ]);
+    }
+
+    fn set_power(&mut self, packet: MotorPacket) {
+        let Self {
+            front_left,
+            front_right,
+            back_left,
+            back_right,
+            voltage_limits,
+            ..
+        } = self;
+
+        set_group_power(
+            front_left,
+            &voltage_limits[0..2],
+            packet.front_left,
+            "Front-left",
+        );
+        set_group_power(
+            front_right,
+            &voltage_limits[2..4],
+            packet.front_right,
+            "Front-right",
+        );
+        set_group_power(
+            back_left,
+            &voltage_limits[4..6],
+            packet.back_left,
+            "Back-left",
+        );
+        set_group_power(
+            back_right,
+            &voltage_limits[6..8],
+            packet.back_right,
+            "Back-right",
+        );
+    }
+
+    fn encoder_packet(&mut self) -> MotorPacket {
+        let timestamp_micros = self.timestamp_micros();
+        MotorPacket {
+            timestamp_micros,
+            front_left: read_position_degrees(&mut self.front_left[0], "Front-left"),
+            front_right: read_position_degrees(&mut self.front_right[0], "Front-right"),
+            back_left: read_position_degrees(&mut self.back_left[0], "Back-left"),
+            back_right: read_position_degrees(&mut self.back_right[0], "Back-right"),
+        }
+    }
+
+    fn telemetry_packet(&mut self) -> TelemetryPacket {
+        let timestamp_micros = self.timestamp_micros();
+        TelemetryPacket {
+            timestamp_micros,
+            motors: [
+                motor_telemetry(&mut self.front_left[0], "Front-left-1"),
+                motor_telemetry(&mut self.front_left[1], "Front-left-2"),
+                motor_telemetry(&mut self.front_right[0], "Front-right-1"),
+                motor_telemetry(&mut self.front_right[1], "Front-right-2"),
+                motor_telemetry(&mut self.back_left[0], "Back-left-1"),
+                motor_telemetry(&mut self.back_left[1], "Back-left-2"),
+                motor_telemetry(&mut self.back_right[0], "Back-right-1"),
+                motor_telemetry(&mut self.back_right[1], "Back-right-2"),
+            ],
+        }
+    }
+
+    fn read_register(&mut self, motor_index: u8, register: Register) -> Option<RegisterValue> {
+        if register == Register::FirmwareVersion {
+            return Some(encode_u32_register(FIRMWARE_VERSION));
+        }
+        if register == Register::TelemetryMode {
+            return Some(encode_u8_register(self.telemetry_mode as u8));
+        }
+        if register == Register::VoltageLimit {
+            let voltage_limit = *self.voltage_limits.get(motor_index as usize)?;
+            return Some(encode_f64_register(voltage_limit));
+        }
+
+        let motor = self.motor_mut(motor_index)?;
+        Some(match register {
+            // Reading live config off the smart port motor can fail exactly
+            // like the feedback reads in `motor_telemetry` (disconnected or
+            // misbehaving motor), so these are handled the same fallible way
+            // rather than unwrapped.
+            Register::Gearset => encode_u8_register(gearset_to_byte(match motor.gearset() {
+                Ok(gearset) => gearset,
+                Err(e) => {
+                    println!("ReadReg: motor {motor_index} gearset read failed: {e:?}");
+                    return None;
+                }
+            })),
+            Register::Direction => encode_u8_register(direction_to_byte(match motor.direction() {
+                Ok(direction) => direction,
+                Err(e) => {
+                    println!("ReadReg: motor {motor_index} direction read failed: {e:?}");
+                    return None;
+                }
+            })),
+            Register::BrakeMode => {
+                encode_u8_register(brake_mode_to_byte(match motor.brake_mode() {
+                    Ok(brake_mode) => brake_mode,
+                    Err(e) => {
+                        println!("ReadReg: motor {motor_index} brake mode read failed: {e:?}");
+                        return None;
+                    }
+                }))
+            }
+            Register::VoltageLimit | Register::FirmwareVersion | Register::TelemetryMode => {
+                unreachable!()
+            }
+        })
+    }
+
+    fn write_register(&mut self, motor_index: u8, register: Register, value: RegisterValue) {
+        match register {
+            Register::FirmwareVersion => {
+                println!("WriteReg: FirmwareVersion is read-only, ignoring");
+            }
+            Register::TelemetryMode => match TelemetryMode::from_byte(value[0]) {
+                Some(mode) => self.telemetry_mode = mode,
+                None => println!("WriteReg: invalid TelemetryMode value {}", value[0]),
+            },
+            Register::VoltageLimit => match self.voltage_limits.get_mut(motor_index as usize) {
+                Some(voltage_limit) => *voltage_limit = decode_f64_register(value),
+                None => println!("WriteReg: unknown motor index {motor_index}"),
+            },
+            Register::Gearset => {
+                let Some(gearset) = gearset_from_byte(value[0]) else {
+                    println!("WriteReg: invalid Gearset value {}", value[0]);
+                    return;
+                };
+                let Some(motor) = self.motor_mut(motor_index) else {
+                    println!("WriteReg: unknown motor index {motor_index}");
+                    return;
+                };
+                if let Err(e) = motor.set_gearset(gearset) {
+                    println!("WriteReg: set_gearset failed: {e:?}");
+                }
+            }
+            Register::Direction => {
+                let Some(direction) = direction_from_byte(value[0]) else {
+                    println!("WriteReg: invalid Direction value {}", value[0]);
+                    return;
+                };
+                let Some(motor) = self.motor_mut(motor_index) else {
+                    println!("WriteReg: unknown motor index {motor_index}");
+                    return;
+                };
+                if let Err(e) = motor.set_direction(direction) {
+                    println!("WriteReg: set_direction failed: {e:?}");
+                }
+            }
+            Register::BrakeMode => {
+                let Some(brake_mode) = brake_mode_from_byte(value[0]) else {
+                    println!("WriteReg: invalid BrakeMode value {}", value[0]);
+                    return;
+                };
+                let Some(motor) = self.motor_mut(motor_index) else {
+                    println!("WriteReg: unknown motor index {motor_index}");
+                    return;
+                };
+                if let Err(e) = motor.set_brake_mode(brake_mode) {
+                    println!("WriteReg: set_brake_mode failed: {e:?}");
+                }
+            }
+        }
+    }
+}
+
+/// Shared handle to the TX port; request/response commands (handled inline
+/// in [`rx_task`]) and the periodic telemetry send (in [`motor_task`]) both
+/// need to write to it. Safe to share via a plain `RefCell` because vexide
+/// tasks are cooperative: only one of the two ever runs at a time, and
+/// neither holds a borrow across an `.await` point.
+type SharedSerialOut = Rc<RefCell<SerialPort>>;
+type SharedDrivetrain = Rc<RefCell<Drivetrain>>;
+
+/// Continuously frames and decodes inbound `COMMAND_MAGIC` packets. `PING`,
+/// `READ_REG`, and `TIME_SYNC` are request/response commands and are answered
+/// immediately; `WRITE_REG` is applied immediately and sends no reply;
+/// `SET_POWER` just updates the shared
+/// latest-power cell and wakes [`motor_task`], which applies it on its own
+/// fixed cadence.
+async fn rx_task(
+    mut rx_serial: SerialPort,
+    tx_serial: SharedSerialOut,
+    drivetrain: SharedDrivetrain,
+    latest_power: Rc<Cell<Option<MotorPacket>>>,
+    new_power_packet: Rc<Signal>,
+    last_power_packet_time: Rc<Cell<time::Instant>>,
+) {
     loop {
-        if let Some(power_packet) = get_power_packet(&mut rx_serial) {
-            println!("Got power packet: {:?}", power_packet);
-            front_lefts.iter_mut().for_each(|m| {
-                m.set_voltage(power_packet.front_left * MOTOR_VOLTAGE_MAX / MOTOR_POWER_MAX)
-                    .expect("Motor set broke");
-            });
-            front_rights.iter_mut().for_each(|m| {
-                m.set_voltage(power_packet.front_right * MOTOR_VOLTAGE_MAX / MOTOR_POWER_MAX)
-                    .expect("Motor set broke");
-            });
-            back_lefts.iter_mut().for_each(|m| {
-                m.set_voltage(power_packet.back_left * MOTOR_VOLTAGE_MAX / MOTOR_POWER_MAX)
-                    .expect("Motor set broke");
-            });
-            back_rights.iter_mut().for_each(|m| {
-                m.set_voltage(power_packet.back_right * MOTOR_VOLTAGE_MAX / MOTOR_POWER_MAX)
-                    .expect("Motor set broke");
-            });
-
-            let encoder_packet = MotorPacket {
-                front_left: front_lefts[0]
-                    .position()
-                    .expect("Motor position broke")
-                    .as_degrees(),
-                front_right: front_rights[0]
-                    .position()
-                    .expect("Motor position broke")
-                    .as_degrees(),
-                back_left: back_lefts[0]
-                    .position()
-                    .expect("Motor position broke")
-                    .as_degrees(),
-                back_right: back_rights[0]
-                    .position()
-                    .expect("Motor position broke")
-                    .as_degrees(),
-            };
-            if send_encoder_packet(&mut tx_serial, &encoder_packet).is_ok() {
-                println!("Sent encoder packet: {:?}", encoder_packet);
+        match get_command(&mut rx_serial).await {
+            Some(BridgeCommand::SetPower(power_packet)) => {
+                println!("Got power packet: {:?}", power_packet);
+                latest_power.set(Some(power_packet));
+                new_power_packet.notify();
+                last_power_packet_time.set(time::Instant::now());
+            }
+            Some(BridgeCommand::Ping) => {
+                let response = CommandResponse {
+                    command: Command::Ping as u8,
+                    motor_index: 0,
+                    register: 0,
+                    _reserved: 0,
+                    value: encode_u32_register(FIRMWARE_VERSION),
+                };
+                if send_response(&mut tx_serial.borrow_mut(), &response).is_err() {
+                    println!("Failed to send PING response");
+                }
             }
+            Some(BridgeCommand::ReadReg {
+                motor_index,
+                register,
+            }) => {
+                let value = drivetrain
+                    .borrow_mut()
+                    .read_register(motor_index, register)
+                    .unwrap_or_default();
+                let response = CommandResponse {
+                    command: Command::ReadReg as u8,
+                    motor_index,
+                    register: register as u8,
+                    _reserved: 0,
+                    value,
+                };
+                if send_response(&mut tx_serial.borrow_mut(), &response).is_err() {
+                    println!("Failed to send READ_REG response");
+                }
+            }
+            Some(BridgeCommand::WriteReg {
+                motor_index,
+                register,
+                value,
+            }) => {
+                drivetrain
+                    .borrow_mut()
+                    .write_register(motor_index, register, value);
+            }
+            Some(BridgeCommand::TimeSync {
+                host_timestamp_micros,
+            }) => {
+                let response = TimeSyncResponse {
+                    host_timestamp_micros,
+                    brain_timestamp_micros: drivetrain.borrow().timestamp_micros(),
+                };
+                if send_time_sync_response(&mut tx_serial.borrow_mut(), &response).is_err() {
+                    println!("Failed to send TIME_SYNC response");
+                }
+            }
+            None => {}
         }
     }
 }
+
+/// Reapplies the latest power packet, runs the watchdog, and sends the next
+/// telemetry packet on a fixed cadence, woken either by the cadence timer or
+/// by a fresh packet arriving in [`rx_task`] — whichever comes first. Idles
+/// between wakeups; `new_power_packet` is a real `Signal`, not a polled flag.
+async fn motor_task(
+    tx_serial: SharedSerialOut,
+    drivetrain: SharedDrivetrain,
+    latest_power: Rc<Cell<Option<MotorPacket>>>,
+    new_power_packet: Rc<Signal>,
+    last_power_packet_time: Rc<Cell<time::Instant>>,
+) {
+    loop {
+        select(new_power_packet.wait(), time::sleep(MOTOR_UPDATE_CADENCE)).await;
+
+        let mut drivetrain = drivetrain.borrow_mut();
+        if time::Instant::now().duration_since(last_power_packet_time.get()) > WATCHDOG_TIMEOUT {
+            println!("Watchdog: no power packet for {WATCHDOG_TIMEOUT:?}, stopping motors");
+            latest_power.set(None);
+            drivetrain.failsafe();
+        } else if let Some(power_packet) = latest_power.get() {
+            drivetrain.set_power(power_packet);
+        }
+
+        let mut tx_serial = tx_serial.borrow_mut();
+        match drivetrain.telemetry_mode {
+            TelemetryMode::Encoder => {
+                let encoder_packet = drivetrain.encoder_packet();
+                if send_encoder_packet(&mut tx_serial, &encoder_packet).is_ok() {
+                    println!("Sent encoder packet: {:?}", encoder_packet);
+                }
+            }
+            TelemetryMode::Full => {
+                let telemetry_packet = drivetrain.telemetry_packet();
+                if send_telemetry_packet(&mut tx_serial, &telemetry_packet).is_ok() {
+                    println!("Sent telemetry packet");
+                }
+            }
+        }
+    }
+}
+
+#[vexide::main]
+async fn main(peripherals: Peripherals) {
+    let rx_serial = SerialPort::open(peripherals.port_1, BAUD_RATE).await;
+    let tx_serial = SerialPort::open(peripherals.port_2, BAUD_RATE).await;
+    let drivetrain = Drivetrain {
+        front_left: [
+            Motor::new(peripherals.port_3, Gearset::Green, Direction::Forward),
+            Motor::new(peripherals.port_4, Gearset::Green, Direction::Forward),
+        ],
+        front_right: [
+            Motor::new(peripherals.port_5, Gearset::Green, Direction::Forward),
+            Motor::new(peripherals.port_6, Gearset::Green, Direction::Forward),
+        ],
+        back_left: [
+            Motor::new(peripherals.port_7, Gearset::Green, Direction::Forward),
+            Motor::new(peripherals.port_8, Gearset::Green, Direction::Forward),
+        ],
+        back_right: [
+            Motor::new(peripherals.port_9, Gearset::Green, Direction::Forward),
+            Motor::new(peripherals.port_10, Gearset::Green, Direction::Forward),
+        ],
+        voltage_limits: [MOTOR_VOLTAGE_MAX; MOTOR_COUNT],
+        telemetry_mode: TelemetryMode::Full,
+        boot_instant: time::Instant::now(),
+    };
+
+    let tx_serial: SharedSerialOut = Rc::new(RefCell::new(tx_serial));
+    let drivetrain: SharedDrivetrain = Rc::new(RefCell::new(drivetrain));
+    let latest_power: Rc<Cell<Option<MotorPacket>>> = Rc::new(Cell::new(None));
+    let new_power_packet = Rc::new(Signal::default());
+    let last_power_packet_time = Rc::new(Cell::new(time::Instant::now()));
+
+    vexide::task::spawn(rx_task(
+        rx_serial,
+        tx_serial.clone(),
+        drivetrain.clone(),
+        latest_power.clone(),
+        new_power_packet.clone(),
+        last_power_packet_time.clone(),
+    ))
+    .detach();
+
+    // Runs on this task, at least once per `MOTOR_UPDATE_CADENCE`, so
+    // that a stalled or slow RX scan (running concurrently in `rx_task`)
+    // never blocks a motor update or telemetry send.
+    motor_task(
+        tx_serial,
+        drivetrain,
+        latest_power,
+        new_power_packet,
+        last_power_packet_time,
+    )
+    .await;
+}